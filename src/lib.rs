@@ -1,10 +1,14 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::io::{self, Cursor, Read};
 use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
 
 use cargo_lock::{self, Lockfile, Package};
 use flate2::read::GzDecoder;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
 use tar::Archive;
 
 /// Result type with the `cargo2port` crate's [`Error`] type.
@@ -28,6 +32,20 @@ pub enum Error {
 
     /// Could not parse the crate specification
     Spec(String),
+
+    /// The SHA-256 of a downloaded `.crate` tarball did not match the checksum recorded for
+    /// it in the lockfile.
+    ChecksumMismatch {
+        name: String,
+        version: String,
+        expected: String,
+        actual: String,
+    },
+
+    /// The registry index is backed by a git repository rather than the sparse HTTP
+    /// protocol, so its `config.json` cannot be fetched with a plain GET. Cloning a
+    /// git index is not implemented.
+    GitRegistryUnsupported(String),
 }
 
 impl fmt::Display for Error {
@@ -38,6 +56,22 @@ impl fmt::Display for Error {
             Error::Tar(error) => error.fmt(f),
             Error::Spec(err) => write!(f, "invalid crate specifier: {}", err),
             Error::MissingLockfile => write!(f, "crate missing Cargo.lock file"),
+            Error::ChecksumMismatch {
+                name,
+                version,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "checksum mismatch for {} {}: expected {}, got {}",
+                name, version, expected, actual
+            ),
+            Error::GitRegistryUnsupported(index) => write!(
+                f,
+                "registry index '{}' is git-based; only sparse registries (index URLs \
+                 prefixed with `sparse+`) are supported",
+                index
+            ),
         }
     }
 }
@@ -66,6 +100,10 @@ impl std::error::Error for Error {}
 // AlignmentMode::Justify, in addition to any other amount calculated.
 const JUSTIFIED_BASE_WIDTH: usize = 5;
 
+/// Default number of concurrent crate downloads used by [`read_packages_from_lockfiles`]
+/// when the caller does not request a specific limit.
+pub const DEFAULT_JOBS: usize = 4;
+
 #[derive(PartialEq)]
 pub enum AlignmentMode {
     Normal,
@@ -94,46 +132,227 @@ pub fn lockfile_from_stdin() -> Result<Lockfile> {
     lockfile_from_str(&contents)
 }
 
-fn lockfile_from_crates_io(crate_spec: &str) -> Result<Lockfile> {
-    let parts: Vec<&str> = crate_spec.split('@').collect();
+/// A `crate:` spec parsed into its name, version, and optional `?registry=` override.
+struct CrateSpec<'a> {
+    name: &'a str,
+    version: &'a str,
+    registry: Option<&'a str>,
+}
 
-    if parts.len() >= 2 {
-        let pkg = download_crate(parts[0], parts[1])?;
-        let cargo_lock = extract_cargo_lock_from_pkg(&pkg)?;
+/// Parse a `crate:name@version` or `crate:name@version?registry=URL` spec (the `crate:`
+/// prefix itself is stripped by the caller). `URL` must be a sparse registry index,
+/// prefixed with `sparse+` the same way cargo records one in `.cargo/config.toml` or in
+/// `Cargo.lock`'s `source` field (e.g. `sparse+https://my-registry.example.com/index/`).
+fn parse_crate_spec(crate_spec: &str) -> Result<CrateSpec<'_>> {
+    let (base, query) = crate_spec.split_once('?').unwrap_or((crate_spec, ""));
+    let (name, version) = base
+        .split_once('@')
+        .ok_or_else(|| Error::Spec(crate_spec.to_string()))?;
+
+    let mut registry = None;
+
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        if let Some(value) = pair.strip_prefix("registry=") {
+            registry = Some(value);
+        }
+    }
 
-        return lockfile_from_str(&cargo_lock);
-    };
+    Ok(CrateSpec {
+        name,
+        version,
+        registry,
+    })
+}
+
+/// A lockfile loaded by [`load_lockfile`], along with the compressed `.crate` download
+/// size and the decompressed `Cargo.lock` size used to obtain it (both `0` for local
+/// paths and stdin, which involve no network I/O or decompression).
+struct LoadedLockfile {
+    lockfile: Lockfile,
+    compressed_bytes: usize,
+    decompressed_bytes: usize,
+}
+
+fn lockfile_from_registry(crate_spec: &str) -> Result<LoadedLockfile> {
+    let spec = parse_crate_spec(crate_spec)?;
+    let pkg = download_crate(spec.name, spec.version, spec.registry)?;
+    let compressed_bytes = pkg.len();
+    let cargo_lock = extract_cargo_lock_from_pkg(&pkg)?;
+    let decompressed_bytes = cargo_lock.len();
+    let lockfile = lockfile_from_str(&cargo_lock)?;
+
+    Ok(LoadedLockfile {
+        lockfile,
+        compressed_bytes,
+        decompressed_bytes,
+    })
+}
+
+/// A crate resolved from a git source rather than crates.io or another registry.
+///
+/// These have no `checksum` in the lockfile, so they cannot be embedded in a
+/// `cargo.crates` block; they are reported separately so portfiles can pin them by
+/// commit instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitCrate {
+    pub name: String,
+    pub version: String,
+    pub url: String,
+    pub commit: String,
+}
 
-    Err(Error::Spec(crate_spec.to_string()))
+/// The result of resolving a set of lockfiles: registry packages ready for
+/// `cargo.crates`, plus any git-sourced crates that need separate handling.
+#[derive(Debug, Default)]
+pub struct ResolvedPackages {
+    pub packages: Vec<Package>,
+    pub git_crates: Vec<GitCrate>,
+}
+
+/// Controls how crates that resolve to more than one version across the merged
+/// lockfiles are handled, selectable alongside [`AlignmentMode`].
+#[derive(PartialEq, Clone, Copy)]
+pub enum DedupPolicy {
+    /// Keep every version of a crate name that appears across the merged lockfiles.
+    /// This is the original behavior: a `BTreeSet<Package>` dedupes on the full
+    /// `(name, version, source)` key, so only exact duplicates collapse.
+    KeepAllVersions,
+
+    /// Collapse each crate name down to its single highest version, discarding any
+    /// older versions that were pulled in elsewhere in the merged dependency graphs.
+    /// Useful for portfiles that want exactly one `cargo.crates` row per crate name.
+    CollapseToHighestVersion,
 }
 
 /// Resolve packages from a vector of Lockfile entries to a de-duplicated sorted vector of
-/// Packages.
+/// Packages, separating out git-sourced crates along the way.
+///
+/// Packages with no source at all are path dependencies (usually workspace members, or the
+/// package owning the Cargo.lock file itself) and are silently excluded. Packages with a
+/// non-git source but no checksum (a registry entry missing its checksum, or e.g. a
+/// vendored directory source) are omitted too, but reported as a warning on stderr since
+/// that is not expected for a real dependency tree.
+///
+/// `[patch]` entries that Cargo resolved but did not end up using (`lockfile.patch.unused`)
+/// are excluded from the result; they never appear in the built dependency graph, so
+/// embedding them in a portfile would pin crates that are never actually fetched. Cargo.lock
+/// itself no longer encodes the older `[replace]` table in the versions [`Lockfile`]
+/// supports: a replaced dependency is simply recorded as its replacement to begin with, so
+/// there is no separate mapping left to apply by the time the lockfile is parsed.
 ///
-/// Packages without a checksum are omitted (this usually happens for the package with the
-/// Cargo.lock file or files being processed).
-pub fn resolve_lockfile_packages(lockfiles: &Vec<Lockfile>) -> Result<Vec<Package>> {
+/// `dedup` selects whether same-named crates resolved at multiple versions are all kept or
+/// collapsed to the highest version; see [`DedupPolicy`].
+pub fn resolve_lockfile_packages(
+    lockfiles: &Vec<Lockfile>,
+    dedup: DedupPolicy,
+) -> Result<ResolvedPackages> {
     let mut packageset: BTreeSet<&Package> = BTreeSet::new();
+    let mut git_crates = Vec::new();
+    let mut omitted = Vec::new();
 
     for lockfile in lockfiles {
+        // `patch.unused` is scoped to this lockfile: a `[patch]` entry one lockfile never
+        // selected says nothing about whether another lockfile's package of the same
+        // name/version is actually in use, so the skip-set must not be merged across
+        // lockfiles.
+        let unused_patches: BTreeSet<(&str, String)> = lockfile
+            .patch
+            .unused
+            .iter()
+            .map(|package| (package.name.as_str(), package.version.to_string()))
+            .collect();
+
         for package in &lockfile.packages {
-            if package.checksum.is_none() {
+            let version = package.version.to_string();
+
+            if unused_patches.contains(&(package.name.as_str(), version.clone())) {
                 continue;
             }
 
-            packageset.insert(package);
+            match &package.source {
+                Some(source) if source.is_git() => {
+                    git_crates.push(GitCrate {
+                        name: package.name.to_string(),
+                        version,
+                        url: source.url().to_string(),
+                        commit: source.precise().unwrap_or_default().to_string(),
+                    });
+                }
+                None => {
+                    // Local path dependency (typically a workspace member); nothing to
+                    // pin in a portfile.
+                }
+                Some(_) if package.checksum.is_some() => {
+                    packageset.insert(package);
+                }
+                Some(source) => {
+                    omitted.push(format!("{} {} ({})", package.name, version, source));
+                }
+            }
         }
     }
 
-    let mut packages = Vec::new();
-
-    for package in packageset {
-        packages.push(package.clone())
+    if !omitted.is_empty() {
+        eprintln!(
+            "warning: omitted {} package(s) with neither a checksum nor a git source: {}",
+            omitted.len(),
+            omitted.join(", ")
+        );
     }
 
+    let mut packages: Vec<Package> = packageset.into_iter().cloned().collect();
     packages.sort();
 
-    Ok(packages)
+    if dedup == DedupPolicy::CollapseToHighestVersion {
+        packages = collapse_to_highest_version(packages);
+    }
+
+    git_crates.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+
+    Ok(ResolvedPackages {
+        packages,
+        git_crates,
+    })
+}
+
+/// Keep only the highest version of each crate name in an already name/version-sorted
+/// package vector.
+fn collapse_to_highest_version(packages: Vec<Package>) -> Vec<Package> {
+    let mut by_name: BTreeMap<String, Package> = BTreeMap::new();
+
+    for package in packages {
+        by_name
+            .entry(package.name.to_string())
+            .and_modify(|kept| {
+                if package.version > kept.version {
+                    *kept = package.clone();
+                }
+            })
+            .or_insert(package);
+    }
+
+    by_name.into_values().collect()
+}
+
+/// Return the portfile `cargo.git_crates` block for crates resolved from a git source,
+/// keyed by name, git URL, and resolved commit hash. Returns an empty string when there
+/// are no git-sourced crates to report.
+pub fn format_cargo_git_crates(git_crates: &[GitCrate]) -> String {
+    if git_crates.is_empty() {
+        return String::new();
+    }
+
+    let mut output = String::from("cargo.git_crates");
+
+    for git_crate in git_crates {
+        output.push_str(" \\\n");
+        output.push_str(&format!(
+            "    {}  {}  {}",
+            git_crate.name, git_crate.url, git_crate.commit
+        ));
+    }
+
+    output
 }
 
 /// Return the portfile `cargo.crates` block given a vector of packages and AlignmentMode.
@@ -217,26 +436,123 @@ pub fn format_cargo_crates(packages: Vec<Package>, mode: AlignmentMode) -> Strin
     output
 }
 
-/// Read the Cargo.lock files from the vector of filenames and resolve into
-/// a de-duplicated, sorted package vector.
+/// Emit a one-line resolution summary to stderr, in the style `cargo package`/`cargo
+/// publish` use for their own `Packaged N files, X KiB (Y KiB compressed)` report.
+///
+/// When `stats` shows at least one `crate:` spec was downloaded, the line also reports
+/// the decompressed `Cargo.lock` size and the compressed `.crate` download size, the
+/// same pair cargo itself reports; otherwise only the deduplicated package count is
+/// shown, since there is nothing to size for locally parsed lockfiles.
+pub fn report_resolution_summary(resolved: &ResolvedPackages, stats: DownloadStats) {
+    let count = resolved.packages.len() + resolved.git_crates.len();
+    let plural = if count == 1 { "" } else { "s" };
+
+    if stats.crates_downloaded > 0 {
+        eprintln!(
+            "Resolved {} crate{}, {} ({} compressed)",
+            count,
+            plural,
+            format_kib(stats.decompressed_bytes),
+            format_kib(stats.compressed_bytes)
+        );
+    } else {
+        eprintln!("Resolved {} crate{}", count, plural);
+    }
+}
+
+fn format_kib(bytes: usize) -> String {
+    format!("{:.1} KiB", bytes as f64 / 1024.0)
+}
+
+fn load_lockfile(name: &str) -> Result<LoadedLockfile> {
+    if name == "-" {
+        Ok(LoadedLockfile {
+            lockfile: lockfile_from_stdin()?,
+            compressed_bytes: 0,
+            decompressed_bytes: 0,
+        })
+    } else if let Some(crate_spec) = name.strip_prefix("crate:") {
+        lockfile_from_registry(crate_spec)
+    } else {
+        Ok(LoadedLockfile {
+            lockfile: lockfile_from_path(name)?,
+            compressed_bytes: 0,
+            decompressed_bytes: 0,
+        })
+    }
+}
+
+/// Download statistics accumulated while resolving `crate:` specs, used to print the
+/// summary line after resolution. All fields stay `0` when every input was a local
+/// lockfile or stdin.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DownloadStats {
+    pub crates_downloaded: usize,
+    /// Total bytes of the downloaded `.crate` tarballs.
+    pub compressed_bytes: usize,
+    /// Total bytes of the `Cargo.lock` extracted from each tarball.
+    pub decompressed_bytes: usize,
+}
+
+/// Read the Cargo.lock files from the vector of filenames and resolve into a
+/// [`ResolvedPackages`], alongside [`DownloadStats`] for any `crate:` specs downloaded
+/// along the way.
+///
+/// `jobs` bounds how many `crate:` specs are downloaded concurrently; pass `0` to use
+/// [`DEFAULT_JOBS`]. This mirrors the `--jobs` knob `cargo package` exposes for its own
+/// `JobsConfig`, so callers building a CLI around this crate can wire a flag straight
+/// through. `dedup` is forwarded to [`resolve_lockfile_packages`].
 ///
 /// This is a cargo2port internal function.
-pub fn read_packages_from_lockfiles(files: &Vec<String>) -> Result<Vec<Package>> {
-    let mut lockfiles: Vec<Lockfile> = vec![];
-
-    for name in files {
-        let lockfile = if name == "-" {
-            lockfile_from_stdin()?
-        } else if let Some(crate_spec) = name.strip_prefix("crate:") {
-            lockfile_from_crates_io(crate_spec)?
-        } else {
-            lockfile_from_path(name)?
-        };
-
-        lockfiles.push(lockfile);
+pub fn read_packages_from_lockfiles(
+    files: &Vec<String>,
+    jobs: usize,
+    dedup: DedupPolicy,
+) -> Result<(ResolvedPackages, DownloadStats)> {
+    let jobs = if jobs == 0 { DEFAULT_JOBS } else { jobs };
+    let worker_count = jobs.min(files.len()).max(1);
+
+    let next = AtomicUsize::new(0);
+    let slots: Vec<Mutex<Option<Result<LoadedLockfile>>>> =
+        (0..files.len()).map(|_| Mutex::new(None)).collect();
+
+    std::thread::scope(|scope| {
+        for _ in 0..worker_count {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+
+                let Some(name) = files.get(index) else {
+                    break;
+                };
+
+                *slots[index].lock().unwrap() = Some(load_lockfile(name));
+            });
+        }
+    });
+
+    let mut lockfiles = Vec::with_capacity(files.len());
+    let mut stats = DownloadStats::default();
+
+    for slot in slots {
+        let result = slot
+            .into_inner()
+            .unwrap()
+            .expect("every slot is filled by a worker before the scope exits");
+
+        let loaded = result?;
+
+        if loaded.compressed_bytes > 0 {
+            stats.crates_downloaded += 1;
+            stats.compressed_bytes += loaded.compressed_bytes;
+            stats.decompressed_bytes += loaded.decompressed_bytes;
+        }
+
+        lockfiles.push(loaded.lockfile);
     }
 
-    resolve_lockfile_packages(&lockfiles)
+    let resolved = resolve_lockfile_packages(&lockfiles, dedup)?;
+
+    Ok((resolved, stats))
 }
 
 fn extract_cargo_lock_from_pkg(pkg: &[u8]) -> Result<String> {
@@ -257,11 +573,285 @@ fn extract_cargo_lock_from_pkg(pkg: &[u8]) -> Result<String> {
     Err(Error::MissingLockfile)
 }
 
-fn download_crate(name: &str, version: &str) -> Result<Vec<u8>> {
-    let url = format!(
-        "https://crates.io/api/v1/crates/{}/{}/download",
-        name, version
-    );
+/// The crates.io download endpoint written as a `dl` template with `{crate}`/`{version}`
+/// markers, hardcoded so the common case does not need a round trip to fetch crates.io's
+/// own `config.json`.
+const CRATES_IO_DOWNLOAD_URL: &str = "https://crates.io/api/v1/crates/{crate}/{version}/download";
+
+/// The subset of a registry's `config.json` this crate needs. See the [Registry Index
+/// Format](https://doc.rust-lang.org/cargo/reference/registry-index.html#index-configuration)
+/// for the full shape.
+#[derive(Deserialize)]
+struct RegistryConfig {
+    dl: String,
+}
+
+/// Resolve the download URL template published by a registry's `config.json` and
+/// substitute in the crate name and version, following the same `{crate}`/`{version}`
+/// placeholders cargo itself recognizes.
+///
+/// Only sparse registries are supported: their index URL is prefixed with `sparse+`
+/// (as cargo itself records them, both in `Cargo.lock`'s `source` field and in
+/// `.cargo/config.toml`'s `[registries]` table), and `config.json` is reachable with a
+/// plain GET under that URL. A git-based index has no such endpoint — cargo clones the
+/// index repository instead — so that case is rejected up front with a clear error
+/// rather than attempting a GET against a git host and surfacing a confusing JSON
+/// parse failure.
+fn registry_download_url(index: &str, name: &str, version: &str) -> Result<String> {
+    // `Cargo.lock` records a sparse index as `sparse+https://...` and a git-based one as
+    // `registry+https://...`. Strip a `registry+` prefix, if present, before looking for
+    // the `sparse+` marker underneath it.
+    let index = index.strip_prefix("registry+").unwrap_or(index);
+
+    let Some(sparse_root) = index.strip_prefix("sparse+") else {
+        return Err(Error::GitRegistryUnsupported(index.to_string()));
+    };
+
+    let config_url = format!("{}/config.json", sparse_root.trim_end_matches('/'));
+    let config: RegistryConfig = reqwest::blocking::get(config_url)?.json()?;
+
+    Ok(expand_dl_template(&config.dl, name, version))
+}
+
+fn expand_dl_template(template: &str, name: &str, version: &str) -> String {
+    if !template.contains("{crate}") && !template.contains("{version}") {
+        // Per the registry index spec, a `dl` template with neither marker (crates.io's
+        // own sparse config.json is written this way) means the client appends the path
+        // itself instead of substituting in place.
+        return format!(
+            "{}/{}/{}/download",
+            template.trim_end_matches('/'),
+            name,
+            version
+        );
+    }
+
+    template
+        .replace("{crate}", name)
+        .replace("{version}", version)
+}
+
+/// Download a `.crate` tarball, either from crates.io or from the registry whose index
+/// URL is given in `registry` (as recorded in the lockfile's `source` field, or supplied
+/// via a `crate:` spec's `?registry=` override).
+fn download_crate(name: &str, version: &str, registry: Option<&str>) -> Result<Vec<u8>> {
+    let url = match registry {
+        Some(index) => registry_download_url(index, name, version)?,
+        None => expand_dl_template(CRATES_IO_DOWNLOAD_URL, name, version),
+    };
+
     let response = reqwest::blocking::get(url)?.bytes()?;
     Ok(response.to_vec())
 }
+
+/// Re-download a single package's `.crate` tarball and verify that its SHA-256 matches the
+/// checksum recorded for it in the lockfile.
+///
+/// Packages without a checksum (git or path dependencies) are skipped rather than treated as
+/// a mismatch.
+pub fn verify_package_checksum(package: &Package) -> Result<()> {
+    let Some(checksum) = &package.checksum else {
+        return Ok(());
+    };
+
+    let expected = checksum
+        .to_string()
+        .rsplit_once(':')
+        .map_or_else(|| checksum.to_string(), |(_, digest)| digest.to_string());
+
+    let name = package.name.as_str();
+    let version = package.version.to_string();
+
+    // `SourceId::url()` strips the `registry+`/`sparse+` scheme prefix; `registry_download_url`
+    // needs that prefix to tell a sparse index from a git-based one, so use the `Display`
+    // impl (which re-encodes it exactly as it appears in `Cargo.lock`'s `source` field)
+    // instead.
+    let registry = package
+        .source
+        .as_ref()
+        .filter(|source| !source.is_default_registry())
+        .map(|source| source.to_string());
+
+    let bytes = download_crate(name, &version, registry.as_deref())?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(&bytes);
+    let actual = format!("{:x}", hasher.finalize());
+
+    if actual.eq_ignore_ascii_case(&expected) {
+        Ok(())
+    } else {
+        Err(Error::ChecksumMismatch {
+            name: name.to_string(),
+            version,
+            expected,
+            actual,
+        })
+    }
+}
+
+/// Verify checksums for a whole set of resolved packages, collecting every mismatch instead
+/// of stopping at the first one.
+pub fn verify_package_checksums(packages: &[Package]) -> Vec<Error> {
+    packages
+        .iter()
+        .filter_map(|package| verify_package_checksum(package).err())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERDE_CHECKSUM: &str =
+        "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+
+    fn lockfile_with_unused_patch() -> Lockfile {
+        lockfile_from_str(&format!(
+            r#"
+version = 3
+
+[[patch.unused]]
+name = "serde"
+version = "1.2.3"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "{SERDE_CHECKSUM}"
+"#,
+        ))
+        .unwrap()
+    }
+
+    fn lockfile_with_real_package() -> Lockfile {
+        lockfile_from_str(&format!(
+            r#"
+version = 3
+
+[[package]]
+name = "serde"
+version = "1.2.3"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "{SERDE_CHECKSUM}"
+"#,
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn unused_patch_in_one_lockfile_does_not_exclude_a_real_package_in_another() {
+        let lockfiles = vec![lockfile_with_unused_patch(), lockfile_with_real_package()];
+
+        let resolved = resolve_lockfile_packages(&lockfiles, DedupPolicy::KeepAllVersions)
+            .expect("resolution should succeed");
+
+        assert!(
+            resolved
+                .packages
+                .iter()
+                .any(|package| package.name.as_str() == "serde"),
+            "a package actually used in one lockfile must not be dropped because another \
+             lockfile's unused patch happens to share its name and version"
+        );
+    }
+
+    #[test]
+    fn collapse_to_highest_version_keeps_only_the_newest_per_name() {
+        let lockfile = lockfile_from_str(
+            r#"
+version = 3
+
+[[package]]
+name = "foo"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb"
+
+[[package]]
+name = "foo"
+version = "2.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "cccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccc"
+"#,
+        )
+        .unwrap();
+
+        let resolved =
+            resolve_lockfile_packages(&vec![lockfile], DedupPolicy::CollapseToHighestVersion)
+                .expect("resolution should succeed");
+
+        assert_eq!(resolved.packages.len(), 1);
+        assert_eq!(resolved.packages[0].version.to_string(), "2.0.0");
+    }
+
+    #[test]
+    fn expand_dl_template_substitutes_known_markers() {
+        let url = expand_dl_template(
+            "https://example.com/api/{crate}/{version}/download",
+            "foo",
+            "1.2.3",
+        );
+
+        assert_eq!(url, "https://example.com/api/foo/1.2.3/download");
+    }
+
+    #[test]
+    fn expand_dl_template_appends_path_when_markers_are_absent() {
+        let url = expand_dl_template("https://crates.io/api/v1/crates", "foo", "1.2.3");
+
+        assert_eq!(url, "https://crates.io/api/v1/crates/foo/1.2.3/download");
+    }
+
+    #[test]
+    fn registry_download_url_rejects_a_git_backed_index() {
+        let err = registry_download_url(
+            "registry+https://github.com/rust-lang/crates.io-index",
+            "foo",
+            "1.2.3",
+        )
+        .expect_err("a git-based index has no config.json endpoint");
+
+        assert!(matches!(err, Error::GitRegistryUnsupported(_)));
+    }
+
+    #[test]
+    fn resolve_lockfile_packages_classifies_git_path_and_omitted_sources() {
+        let lockfile = lockfile_from_str(
+            r#"
+version = 3
+
+[[package]]
+name = "normal-crate"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "dddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddd"
+
+[[package]]
+name = "git-crate"
+version = "0.1.0"
+source = "git+https://github.com/example/git-crate#1111111111111111111111111111111111111111"
+
+[[package]]
+name = "path-crate"
+version = "0.1.0"
+
+[[package]]
+name = "no-checksum-crate"
+version = "0.1.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#,
+        )
+        .unwrap();
+
+        let resolved = resolve_lockfile_packages(&vec![lockfile], DedupPolicy::KeepAllVersions)
+            .expect("resolution should succeed");
+
+        assert_eq!(resolved.packages.len(), 1);
+        assert_eq!(resolved.packages[0].name.as_str(), "normal-crate");
+
+        assert_eq!(resolved.git_crates.len(), 1);
+        assert_eq!(resolved.git_crates[0].name, "git-crate");
+        assert_eq!(
+            resolved.git_crates[0].commit,
+            "1111111111111111111111111111111111111111"
+        );
+    }
+}